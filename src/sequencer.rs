@@ -0,0 +1,197 @@
+// Generative sequencer: turn the evolving board into music.
+//
+// The board is read as a step sequencer. A playhead sweeps one column per tick at a user-set
+// BPM; every column is a time step and every row is a pitch drawn from a chosen scale. When
+// the playhead crosses a column it emits note-on events for the live cells there, so drifting
+// patterns produce evolving melodies.
+//
+// Output is pluggable. The only backend today writes a Standard MIDI File of the run, but the
+// event model (absolute ticks + note numbers) leaves room for a realtime backend later.
+use std::fs;
+use std::io;
+
+// A quarter note's worth of ticks; also the MIDI file's division. One playhead step lasts a
+// quarter note.
+const TICKS_PER_STEP: u32 = 480;
+
+// A recorded note spanning a single step.
+struct Note {
+    tick_on: u32,
+    tick_off: u32,
+    pitch: u8,
+}
+
+pub struct Sequencer {
+    bpm: u32,
+    root: u8,
+    scale: Vec<u8>,
+    columns: i64,
+    playhead: i64,
+    step: u32,
+    notes: Vec<Note>,
+}
+
+impl Sequencer {
+    pub fn new(bpm: u32, root: u8, scale: Vec<u8>, columns: i64) -> Self {
+        Sequencer {
+            bpm: bpm.max(1),
+            root,
+            scale,
+            columns: columns.max(1),
+            playhead: 0,
+            step: 0,
+            notes: Vec::new(),
+        }
+    }
+
+    // Duration of a single playhead step.
+    pub fn step_duration(&self) -> std::time::Duration {
+        std::time::Duration::from_millis(60_000 / self.bpm as u64)
+    }
+
+    pub fn playhead_column(&self) -> i64 {
+        self.playhead
+    }
+
+    pub fn note_count(&self) -> usize {
+        self.notes.len()
+    }
+
+    // Emit note-on/off events for the live cells in the current column, one per live row.
+    pub fn emit(&mut self, rows: &[i64]) {
+        let tick_on = self.step * TICKS_PER_STEP;
+        let tick_off = tick_on + TICKS_PER_STEP;
+
+        for &row in rows {
+            if let Some(pitch) = self.pitch_for_row(row) {
+                self.notes.push(Note { tick_on, tick_off, pitch });
+            }
+        }
+    }
+
+    // Advance the playhead one column, wrapping at the region width, and move time forward a
+    // step.
+    pub fn advance(&mut self) {
+        self.playhead = (self.playhead + 1) % self.columns;
+        self.step += 1;
+    }
+
+    // Quantize a row to a MIDI note: lower rows sit near the root, higher rows climb the scale
+    // octave by octave. Rows that fall outside the MIDI range are dropped.
+    fn pitch_for_row(&self, row: i64) -> Option<u8> {
+        if row < 0 || self.scale.is_empty() {
+            return None;
+        }
+
+        let degrees = self.scale.len() as i64;
+        let octave = row / degrees;
+        let degree = (row % degrees) as usize;
+        let pitch = self.root as i64 + 12 * octave + self.scale[degree] as i64;
+
+        if (0..=127).contains(&pitch) {
+            Some(pitch as u8)
+        } else {
+            None
+        }
+    }
+
+    // Write the recorded run as a type-0 Standard MIDI File.
+    pub fn write_midi(&self, path: &str) -> io::Result<()> {
+        // (absolute tick, message bytes). Note-offs sort before note-ons at the same tick so a
+        // re-triggered pitch is released before it sounds again.
+        let mut events: Vec<(u32, u8, Vec<u8>)> = Vec::new();
+
+        let mpqn = 60_000_000 / self.bpm;
+        events.push((0, 0, vec![
+            0xFF, 0x51, 0x03,
+            ((mpqn >> 16) & 0xFF) as u8,
+            ((mpqn >> 8) & 0xFF) as u8,
+            (mpqn & 0xFF) as u8,
+        ]));
+
+        for note in &self.notes {
+            events.push((note.tick_off, 1, vec![0x80, note.pitch, 0]));
+            events.push((note.tick_on, 2, vec![0x90, note.pitch, 64]));
+        }
+
+        events.sort_by_key(|(tick, order, _)| (*tick, *order));
+
+        let mut body = Vec::new();
+        let mut prev = 0u32;
+        for (tick, _, msg) in &events {
+            write_vlq(&mut body, tick - prev);
+            body.extend_from_slice(msg);
+            prev = *tick;
+        }
+        write_vlq(&mut body, 0);
+        body.extend_from_slice(&[0xFF, 0x2F, 0x00]);
+
+        let mut out = Vec::new();
+        out.extend_from_slice(b"MThd");
+        out.extend_from_slice(&6u32.to_be_bytes());
+        out.extend_from_slice(&0u16.to_be_bytes());
+        out.extend_from_slice(&1u16.to_be_bytes());
+        out.extend_from_slice(&(TICKS_PER_STEP as u16).to_be_bytes());
+        out.extend_from_slice(b"MTrk");
+        out.extend_from_slice(&(body.len() as u32).to_be_bytes());
+        out.extend_from_slice(&body);
+
+        fs::write(path, out)
+    }
+}
+
+// Semitone offsets of a named scale within one octave. Unknown names fall back to the full
+// chromatic scale.
+pub fn scale_from_name(name: &str) -> Vec<u8> {
+    match name.to_ascii_lowercase().as_str() {
+        "major" => vec![0, 2, 4, 5, 7, 9, 11],
+        "minor" => vec![0, 2, 3, 5, 7, 8, 10],
+        "pentatonic" => vec![0, 3, 5, 7, 10],
+        _ => (0..12).collect(),
+    }
+}
+
+// Encode `value` as a MIDI variable-length quantity (7 bits per byte, high bit as a
+// continuation flag).
+fn write_vlq(out: &mut Vec<u8>, value: u32) {
+    let mut buffer = value & 0x7F;
+    let mut value = value >> 7;
+    while value > 0 {
+        buffer <<= 8;
+        buffer |= (value & 0x7F) | 0x80;
+        value >>= 7;
+    }
+
+    loop {
+        out.push((buffer & 0xFF) as u8);
+        if buffer & 0x80 != 0 {
+            buffer >>= 8;
+        } else {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vlq_encodes_multi_byte_values() {
+        let mut out = Vec::new();
+        write_vlq(&mut out, 480);
+        assert_eq!(out, vec![0x83, 0x60]);
+
+        let mut zero = Vec::new();
+        write_vlq(&mut zero, 0);
+        assert_eq!(zero, vec![0x00]);
+    }
+
+    #[test]
+    fn pitch_climbs_the_scale_by_octave() {
+        let seq = Sequencer::new(120, 60, scale_from_name("major"), 8);
+        assert_eq!(seq.pitch_for_row(0), Some(60));
+        assert_eq!(seq.pitch_for_row(7), Some(72));
+        assert_eq!(seq.pitch_for_row(-1), None);
+    }
+}